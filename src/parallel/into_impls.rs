@@ -0,0 +1,58 @@
+// Copyright 2019 bluss and ndarray developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `IntoParallelIterator` and [`IndexedProducer`](trait.IndexedProducer.html)
+//! implementations for ndarray's own iterators.
+
+use rayon::iter::IntoParallelIterator;
+
+use crate::iter::{AxisIter, AxisIterMut};
+use crate::Dimension;
+
+use super::par::{new_parallel, IndexedProducer, Parallel};
+
+macro_rules! axis_iter_impl {
+    ($iter_name:ident, $elem_bound:ident) => {
+        impl<'a, A, D> IndexedProducer for $iter_name<'a, A, D>
+        where
+            D: Dimension,
+            A: $elem_bound,
+        {
+            type Item = <Self as Iterator>::Item;
+            type IntoIter = Self;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self
+            }
+
+            fn split_at(self, index: usize) -> (Self, Self) {
+                self.split_at(index)
+            }
+
+            fn len(&self) -> usize {
+                ExactSizeIterator::len(self)
+            }
+        }
+
+        impl<'a, A, D> IntoParallelIterator for $iter_name<'a, A, D>
+        where
+            D: Dimension,
+            A: $elem_bound,
+        {
+            type Item = <Self as Iterator>::Item;
+            type Iter = Parallel<Self>;
+
+            fn into_par_iter(self) -> Self::Iter {
+                new_parallel(self)
+            }
+        }
+    };
+}
+
+axis_iter_impl!(AxisIter, Sync);
+axis_iter_impl!(AxisIterMut, Send);
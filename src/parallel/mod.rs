@@ -95,7 +95,7 @@ pub mod prelude {
     IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator};
 }
 
-pub use self::par::Parallel;
+pub use self::par::{MultiZip, Parallel, ZipInPool};
 
 mod par;
 mod ext_traits;
@@ -0,0 +1,644 @@
+// Copyright 2019 bluss and ndarray developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parallel support for `Zip`.
+//!
+//! `Zip::par_apply` (and the `par_fold` combinator it is built on) split the
+//! zipped producers in half recursively, handing each half off to rayon's
+//! thread pool, until the remaining chunk is small enough to be visited in a
+//! plain sequential pass. Because the producers may not agree on a single
+//! linear element order (for example when they have different memory
+//! layouts), the split is driven through rayon's *unindexed* producer and
+//! consumer traits rather than the indexed `Producer`/`Consumer` pair used
+//! for ordinary slices.
+
+use rayon::iter::plumbing::{
+    bridge, bridge_unindexed, Consumer, Folder, Producer, ProducerCallback, Reducer,
+    UnindexedConsumer, UnindexedProducer,
+};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::Dimension;
+use crate::NdProducer;
+use crate::Zip;
+
+/// Chunks of this size or smaller are folded sequentially instead of being
+/// split further; this bounds the number of rayon tasks spawned for small
+/// inputs where the splitting overhead would dominate the actual work.
+const FOLD_MIN_LEN: usize = 1_024;
+
+struct ZipProducer<Parts, D> {
+    zip: Zip<Parts, D>,
+}
+
+/// A `Zip` bound to a caller-supplied thread pool, returned by
+/// [`Zip::with_nested_pool`](struct.Zip.html#method.with_nested_pool).
+///
+/// This lets two-level parallelism (an outer `par_apply` whose closure does
+/// further parallel work of its own) compose without the outer traversal
+/// oversubscribing every core before the inner work even starts: the outer
+/// level is confined to this pool. Parallel work started inside the closure
+/// runs in this same pool too, unless the closure installs it into another
+/// pool (for example with `inner_pool.install(...)`).
+pub struct ZipInPool<'p, Parts, D> {
+    zip: Zip<Parts, D>,
+    pool: &'p ::rayon::ThreadPool,
+}
+
+struct ZipFoldConsumer<'f, ID, F, R> {
+    identity: &'f ID,
+    fold_op: &'f F,
+    reduce_op: &'f R,
+}
+
+impl<'f, ID, F, R> Clone for ZipFoldConsumer<'f, ID, F, R> {
+    fn clone(&self) -> Self {
+        ZipFoldConsumer {
+            identity: self.identity,
+            fold_op: self.fold_op,
+            reduce_op: self.reduce_op,
+        }
+    }
+}
+
+struct ZipFoldFolder<'f, F, T> {
+    fold_op: &'f F,
+    item: T,
+}
+
+struct ZipFoldReducer<'f, R> {
+    reduce_op: &'f R,
+}
+
+impl<'f, F, T, Item> Folder<Item> for ZipFoldFolder<'f, F, T>
+where
+    F: Fn(T, Item) -> T,
+{
+    type Result = T;
+
+    fn consume(self, item: Item) -> Self {
+        ZipFoldFolder {
+            fold_op: self.fold_op,
+            item: (self.fold_op)(self.item, item),
+        }
+    }
+
+    fn complete(self) -> T {
+        self.item
+    }
+
+    fn full(&self) -> bool {
+        false
+    }
+}
+
+impl<'f, R, T> Reducer<T> for ZipFoldReducer<'f, R>
+where
+    R: Fn(T, T) -> T,
+{
+    fn reduce(self, left: T, right: T) -> T {
+        (self.reduce_op)(left, right)
+    }
+}
+
+impl<'f, ID, F, R, T, Item> rayon::iter::plumbing::Consumer<Item> for ZipFoldConsumer<'f, ID, F, R>
+where
+    ID: Fn() -> T + Sync,
+    F: Fn(T, Item) -> T + Sync,
+    R: Fn(T, T) -> T + Sync,
+    T: Send,
+{
+    type Folder = ZipFoldFolder<'f, F, T>;
+    type Reducer = ZipFoldReducer<'f, R>;
+    type Result = T;
+
+    fn split_at(self, _index: usize) -> (Self, Self, Self::Reducer) {
+        let reducer = ZipFoldReducer { reduce_op: self.reduce_op };
+        (self.clone(), self.clone(), reducer)
+    }
+
+    fn into_folder(self) -> Self::Folder {
+        ZipFoldFolder {
+            fold_op: self.fold_op,
+            item: (self.identity)(),
+        }
+    }
+
+    fn full(&self) -> bool {
+        false
+    }
+}
+
+impl<'f, ID, F, R, T, Item> UnindexedConsumer<Item> for ZipFoldConsumer<'f, ID, F, R>
+where
+    ID: Fn() -> T + Sync,
+    F: Fn(T, Item) -> T + Sync,
+    R: Fn(T, T) -> T + Sync,
+    T: Send,
+{
+    fn split_off_left(&self) -> Self {
+        self.clone()
+    }
+
+    fn to_reducer(&self) -> Self::Reducer {
+        ZipFoldReducer { reduce_op: self.reduce_op }
+    }
+}
+
+macro_rules! zip_par_impl {
+    ($([$($p:ident)*],)+) => {
+        $(
+        #[allow(non_snake_case)]
+        impl<D, $($p),*> UnindexedProducer for ZipProducer<($($p,)*), D>
+        where
+            D: Dimension,
+            $($p: NdProducer<Dim = D> + Send,)*
+        {
+            type Item = ($($p::Item,)*);
+
+            fn split(self) -> (Self, Option<Self>) {
+                if self.zip.size() <= FOLD_MIN_LEN {
+                    (self, None)
+                } else {
+                    let (a, b) = self.zip.split();
+                    (ZipProducer { zip: a }, Some(ZipProducer { zip: b }))
+                }
+            }
+
+            fn fold_with<Fld>(self, folder: Fld) -> Fld
+            where
+                Fld: Folder<Self::Item>,
+            {
+                let mut folder = Some(folder);
+                self.zip.apply(|$($p: $p::Item),*| {
+                    let f = folder.take().unwrap();
+                    folder = Some(if f.full() { f } else { f.consume(($($p,)*)) });
+                });
+                folder.unwrap()
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<D, $($p),*> Zip<($($p,)*), D>
+        where
+            D: Dimension,
+            $($p: NdProducer<Dim = D> + Send,)*
+        {
+            /// Apply a function to all elements, visiting elements in arbitrary
+            /// order, using the rayon thread pool.
+            ///
+            /// This is the parallel counterpart to `.apply()`: it has no
+            /// ordering guarantee, so it's only suitable for commutative,
+            /// side-effecting work.
+            pub fn par_apply<F>(self, function: F)
+            where
+                F: Fn($($p::Item),*) + Sync + Send,
+            {
+                self.par_fold(|| (), move |(), $($p: $p::Item),*| function($($p),*), |(), ()| ())
+            }
+
+            /// Split the zipped producers across the thread pool, folding each
+            /// chunk into a local accumulator with `fold_op`, then combine the
+            /// partial accumulators pairwise with `reduce_op`.
+            ///
+            /// `identity` is called once per chunk to produce that chunk's
+            /// initial accumulator. `fold_op` and `reduce_op` must be
+            /// associative (with `identity`'s result acting as a neutral
+            /// element) so that the final result does not depend on how the
+            /// work happened to be split and scheduled.
+            ///
+            /// This lets you compute reductions — a parallel dot product, or a
+            /// sum of per-element differences across several arrays — without
+            /// allocating an intermediate array just to reduce it afterward.
+            ///
+            /// ```
+            /// extern crate ndarray;
+            ///
+            /// use ndarray::Array1;
+            /// use ndarray::Zip;
+            ///
+            /// fn main() {
+            ///     let a = Array1::from(vec![1., 2., 3., 4.]);
+            ///     let b = Array1::from(vec![1., 1., 1., 1.]);
+            ///
+            ///     let dot = Zip::from(&a)
+            ///         .and(&b)
+            ///         .par_fold(|| 0., |acc, &a, &b| acc + a * b, |a, b| a + b);
+            ///
+            ///     assert_eq!(dot, 10.);
+            /// }
+            /// ```
+            pub fn par_fold<ID, F, R, T>(self, identity: ID, fold_op: F, reduce_op: R) -> T
+            where
+                ID: Fn() -> T + Sync,
+                F: Fn(T, $($p::Item),*) -> T + Sync,
+                R: Fn(T, T) -> T + Sync,
+                T: Send,
+            {
+                let fold_op = move |acc: T, item: ($($p::Item,)*)| {
+                    let ($($p,)*) = item;
+                    fold_op(acc, $($p),*)
+                };
+                bridge_unindexed(
+                    ZipProducer { zip: self },
+                    ZipFoldConsumer {
+                        identity: &identity,
+                        fold_op: &fold_op,
+                        reduce_op: &reduce_op,
+                    },
+                )
+            }
+
+            /// Like [`par_apply`](#method.par_apply), but runs the traversal
+            /// inside `pool` instead of the global rayon thread pool.
+            ///
+            /// Use a dedicated pool for the outer traversal when each closure
+            /// invocation spawns its own inner parallel work (for example a
+            /// nested `.into_par_iter()` over a row): without this, the outer
+            /// `par_apply` would already have saturated every core before the
+            /// first inner task is even spawned, and the two levels would
+            /// contend for the same threads. Running the outer level in a
+            /// smaller, separate pool bounds that contention. Parallel work
+            /// started inside `function` runs in `pool` as well, unless
+            /// `function` installs it into another pool.
+            pub fn par_apply_in<F>(self, pool: &::rayon::ThreadPool, function: F)
+            where
+                F: Fn($($p::Item),*) + Sync + Send,
+            {
+                pool.install(move || self.par_apply(function))
+            }
+
+            /// Bind this `Zip` to `pool`, so that a later `.par_apply()` or
+            /// `.par_fold()` call runs inside it rather than the global pool.
+            ///
+            /// ```
+            /// extern crate ndarray;
+            /// extern crate rayon;
+            ///
+            /// use ndarray::Array2;
+            /// use ndarray::Zip;
+            ///
+            /// fn main() {
+            ///     let outer_pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+            ///
+            ///     let a = Array2::<f64>::zeros((4, 4));
+            ///     let mut b = Array2::<f64>::zeros((4, 4));
+            ///
+            ///     Zip::from(&mut b)
+            ///         .and(&a)
+            ///         .with_nested_pool(&outer_pool)
+            ///         .par_apply(|b, &a| *b = a);
+            /// }
+            /// ```
+            pub fn with_nested_pool(self, pool: &::rayon::ThreadPool) -> ZipInPool<'_, ($($p,)*), D> {
+                ZipInPool { zip: self, pool }
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<'p, D, $($p),*> ZipInPool<'p, ($($p,)*), D>
+        where
+            D: Dimension,
+            $($p: NdProducer<Dim = D> + Send,)*
+        {
+            /// Run the bound `Zip`'s `.par_apply()` inside the pool supplied
+            /// to [`with_nested_pool`](struct.Zip.html#method.with_nested_pool).
+            pub fn par_apply<F>(self, function: F)
+            where
+                F: Fn($($p::Item),*) + Sync + Send,
+            {
+                self.pool.install(move || self.zip.par_apply(function))
+            }
+
+            /// Run the bound `Zip`'s `.par_fold()` inside the pool supplied
+            /// to [`with_nested_pool`](struct.Zip.html#method.with_nested_pool).
+            pub fn par_fold<ID, F, R, T>(self, identity: ID, fold_op: F, reduce_op: R) -> T
+            where
+                ID: Fn() -> T + Sync,
+                F: Fn(T, $($p::Item),*) -> T + Sync,
+                R: Fn(T, T) -> T + Sync,
+                T: Send,
+            {
+                self.pool.install(move || self.zip.par_fold(identity, fold_op, reduce_op))
+            }
+        }
+        )+
+    }
+}
+
+zip_par_impl! {
+    [P1],
+    [P1 P2],
+    [P1 P2 P3],
+    [P1 P2 P3 P4],
+    [P1 P2 P3 P4 P5],
+    [P1 P2 P3 P4 P5 P6],
+}
+
+/// A splittable, indexed source for a [`Parallel`](struct.Parallel.html)
+/// iterator.
+///
+/// This is ndarray's equivalent of rayon's own `Producer` trait, except it is
+/// implemented directly for ndarray's iterators (e.g. `AxisIter`) and for
+/// tuples of them (see [`MultiZip`](struct.MultiZip.html)), so that `.len()`
+/// is available without consuming the producer.
+pub trait IndexedProducer: Sized + Send {
+    /// The type of item that this producer yields.
+    type Item;
+    /// The sequential iterator that a leaf-sized chunk is finished off with.
+    type IntoIter: Iterator<Item = Self::Item> + ExactSizeIterator + DoubleEndedIterator;
+
+    /// Convert into the final sequential iterator.
+    fn into_iter(self) -> Self::IntoIter;
+
+    /// Split into two producers at `index`, the first containing the items
+    /// up to (but not including) `index`.
+    fn split_at(self, index: usize) -> (Self, Self);
+
+    /// The number of items remaining in this producer.
+    fn len(&self) -> usize;
+}
+
+/// A parallel iterator over an [`IndexedProducer`](trait.IndexedProducer.html).
+///
+/// This is the type returned by `.into_par_iter()` for `.axis_iter()` /
+/// `.axis_iter_mut()` and for [`MultiZip`](struct.MultiZip.html); it is
+/// indexed and exact length, just like the sequential iterators it wraps.
+#[derive(Copy, Clone, Debug)]
+pub struct Parallel<P> {
+    iter: P,
+    min_len: usize,
+    max_len: usize,
+}
+
+pub(crate) fn new_parallel<P>(iter: P) -> Parallel<P> {
+    Parallel {
+        iter,
+        min_len: 1,
+        max_len: usize::max_value(),
+    }
+}
+
+impl<P> Parallel<P> {
+    /// Set the minimum number of lanes that a single rayon task may be given.
+    ///
+    /// A chunk is not split if either half would be shorter than `min_len`,
+    /// so raising it trades finer-grained load balancing for less per-task
+    /// overhead — useful when the per-lane work is small enough that task
+    /// scheduling would otherwise dominate.
+    ///
+    /// ```
+    /// extern crate ndarray;
+    ///
+    /// use ndarray::Array2;
+    /// use ndarray::Axis;
+    /// use ndarray::parallel::prelude::*;
+    ///
+    /// fn main() {
+    ///     let a = Array2::<f64>::zeros((256, 4));
+    ///     a.axis_iter(Axis(0))
+    ///         .into_par_iter()
+    ///         .with_min_len(32)
+    ///         .for_each(|row| debug_assert_eq!(row.len(), 4));
+    /// }
+    /// ```
+    pub fn with_min_len(self, min_len: usize) -> Self {
+        Parallel { min_len, ..self }
+    }
+
+    /// Set the maximum number of lanes that a single rayon task may be given.
+    ///
+    /// Chunks longer than `max_len` are split even if they would otherwise be
+    /// handed to one task whole, forcing finer splitting for workloads whose
+    /// per-lane cost is high enough that a large contiguous chunk would
+    /// create an imbalance between threads.
+    pub fn with_max_len(self, max_len: usize) -> Self {
+        Parallel { max_len, ..self }
+    }
+}
+
+impl<P> ParallelIterator for Parallel<P>
+where
+    P: IndexedProducer,
+    P::Item: Send,
+{
+    type Item = P::Item;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+impl<P> IndexedParallelIterator for Parallel<P>
+where
+    P: IndexedProducer,
+    P::Item: Send,
+{
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+
+    fn drive<C>(self, consumer: C) -> C::Result
+    where
+        C: Consumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+    where
+        CB: ProducerCallback<Self::Item>,
+    {
+        callback.callback(self)
+    }
+}
+
+impl<P> Producer for Parallel<P>
+where
+    P: IndexedProducer,
+    P::Item: Send,
+{
+    type Item = P::Item;
+    type IntoIter = P::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter.into_iter()
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let (a, b) = self.iter.split_at(index);
+        (
+            Parallel { iter: a, min_len: self.min_len, max_len: self.max_len },
+            Parallel { iter: b, min_len: self.min_len, max_len: self.max_len },
+        )
+    }
+
+    fn min_len(&self) -> usize {
+        self.min_len
+    }
+
+    fn max_len(&self) -> usize {
+        self.max_len
+    }
+}
+
+/// A tuple of ndarray producers (for example axis iterators) zipped together
+/// into a single parallel iterator of tuples, truncated to the length of the
+/// shortest member — the tuple analogue of rayon's own `multizip`.
+///
+/// ```
+/// extern crate ndarray;
+///
+/// use ndarray::{Array2, Axis};
+/// use ndarray::parallel::prelude::*;
+/// use ndarray::parallel::MultiZip;
+///
+/// fn main() {
+///     let a = Array2::<f64>::zeros((4, 3));
+///     let b = Array2::<f64>::from_elem((4, 3), 1.);
+///     let mut c = Array2::<f64>::zeros((4, 3));
+///
+///     MultiZip::new((a.axis_iter(Axis(0)), b.axis_iter(Axis(0)), c.axis_iter_mut(Axis(0))))
+///         .into_par_iter()
+///         .for_each(|(a, b, mut c)| c.assign(&(&a + &b)));
+///
+///     assert_eq!(c, b);
+/// }
+/// ```
+pub struct MultiZip<Parts>(Parts);
+
+impl<Parts> MultiZip<Parts> {
+    /// Create a `MultiZip` from a tuple of ndarray producers, up to 6 long.
+    pub fn new(parts: Parts) -> Self {
+        MultiZip(parts)
+    }
+}
+
+#[doc(hidden)]
+pub struct MultiZipProducer<Parts> {
+    parts: Parts,
+}
+
+#[doc(hidden)]
+pub struct MultiZipIter<Parts> {
+    parts: Parts,
+}
+
+macro_rules! multizip_impl {
+    ($([$($p:ident)*],)+) => {
+        $(
+        #[allow(non_snake_case)]
+        impl<$($p),*> Iterator for MultiZipIter<($($p,)*)>
+        where
+            $($p: Iterator,)*
+        {
+            type Item = ($($p::Item,)*);
+
+            fn next(&mut self) -> Option<Self::Item> {
+                let ($(ref mut $p,)*) = self.parts;
+                Some(($($p.next()?,)*))
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<$($p),*> ExactSizeIterator for MultiZipIter<($($p,)*)>
+        where
+            $($p: ExactSizeIterator,)*
+        {
+            fn len(&self) -> usize {
+                let ($(ref $p,)*) = self.parts;
+                let lens = [$($p.len()),*];
+                lens.iter().cloned().min().unwrap_or(0)
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<$($p),*> DoubleEndedIterator for MultiZipIter<($($p,)*)>
+        where
+            $($p: DoubleEndedIterator + ExactSizeIterator,)*
+        {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                let min_len = ExactSizeIterator::len(self);
+                if min_len == 0 {
+                    return None;
+                }
+                let ($(ref mut $p,)*) = self.parts;
+                // A member longer than the shortest one has elements beyond
+                // `min_len` at its tail that fall outside the
+                // (shortest-length-truncated) zip; they must be discarded,
+                // not paired up, before popping the aligned element that
+                // actually belongs to the zipped sequence.
+                $(
+                    while $p.len() > min_len {
+                        $p.next_back();
+                    }
+                )*
+                Some(($($p.next_back()?,)*))
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<$($p),*> IndexedProducer for MultiZipProducer<($($p,)*)>
+        where
+            $($p: IndexedProducer,)*
+        {
+            type Item = ($($p::Item,)*);
+            type IntoIter = MultiZipIter<($($p::IntoIter,)*)>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                let ($($p,)*) = self.parts;
+                MultiZipIter { parts: ($($p.into_iter(),)*) }
+            }
+
+            fn split_at(self, index: usize) -> (Self, Self) {
+                let ($($p,)*) = self.parts;
+                $(let $p = $p.split_at(index);)*
+                (
+                    MultiZipProducer { parts: ($($p.0,)*) },
+                    MultiZipProducer { parts: ($($p.1,)*) },
+                )
+            }
+
+            fn len(&self) -> usize {
+                let ($(ref $p,)*) = self.parts;
+                let lens = [$($p.len()),*];
+                lens.iter().cloned().min().unwrap_or(0)
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<$($p),*> IntoParallelIterator for MultiZip<($($p,)*)>
+        where
+            $($p: IndexedProducer,)*
+            $($p::Item: Send,)*
+        {
+            type Item = ($($p::Item,)*);
+            type Iter = Parallel<MultiZipProducer<($($p,)*)>>;
+
+            fn into_par_iter(self) -> Self::Iter {
+                new_parallel(MultiZipProducer { parts: self.0 })
+            }
+        }
+        )+
+    }
+}
+
+multizip_impl! {
+    [P1 P2],
+    [P1 P2 P3],
+    [P1 P2 P3 P4],
+    [P1 P2 P3 P4 P5],
+    [P1 P2 P3 P4 P5 P6],
+}
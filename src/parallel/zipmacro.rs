@@ -0,0 +1,90 @@
+// Copyright 2019 bluss and ndarray developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `azip!` and its parallel sibling `par_azip!`.
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __azip_impl {
+    (@parse ($($exprs:expr),*) ($($pats:pat),*) ($pat:pat in $expr:expr , $($rest:tt)*) $body:block [$apply_method:ident]) => {
+        $crate::__azip_impl!(@parse ($($exprs,)* $expr) ($($pats,)* $pat) ($($rest)*) $body [$apply_method])
+    };
+    (@parse ($($exprs:expr),*) ($($pats:pat),*) () $body:block [$apply_method:ident]) => {
+        $crate::__azip_impl!(@finish ($($exprs),*) ($($pats),*) $body [$apply_method])
+    };
+    (@finish ($first:expr $(, $exprs:expr)*) ($($pats:pat),*) $body:block [$apply_method:ident]) => {
+        $crate::Zip::from($first) $(.and($exprs))* .$apply_method(|$($pats),*| $body)
+    };
+}
+
+/// Array zip macro: lock-step, element-wise traversal of multiple arrays,
+/// views and producers.
+///
+/// Each binding takes the form `pattern in expr`, where `expr` is anything
+/// that implements `NdProducer` (arrays, array views, and other `Zip`
+/// producers) and `pattern` is the name (optionally prefixed with `&` or
+/// `&mut` to match the producer's item type) that the element is bound to
+/// inside the body.
+///
+/// `azip!((a in &mut c, &b in &arr_b, &d in &arr_d) { *a = b + d; })` expands
+/// to `Zip::from(&mut c).and(&arr_b).and(&arr_d).apply(|a, &b, &d| { *a = b + d; })`.
+///
+/// ```
+/// extern crate ndarray;
+///
+/// use ndarray::Array2;
+/// use ndarray::azip;
+///
+/// fn main() {
+///     let arr_b = Array2::<f64>::from_elem((2, 2), 1.);
+///     let arr_d = Array2::<f64>::from_elem((2, 2), 2.);
+///     let mut c = Array2::<f64>::zeros((2, 2));
+///
+///     azip!((a in &mut c, &b in &arr_b, &d in &arr_d) { *a = b + d; });
+///
+///     assert_eq!(c, Array2::from_elem((2, 2), 3.));
+/// }
+/// ```
+#[macro_export]
+macro_rules! azip {
+    ( ($($t:tt)*) $body:block ) => {
+        $crate::__azip_impl!(@parse () () ($($t)*,) $body [apply])
+    };
+}
+
+/// Parallel counterpart to [`azip!`](macro.azip.html), using rayon's thread
+/// pool in place of a sequential traversal.
+///
+/// Takes the exact same binding syntax as `azip!`, but expands to
+/// `Zip::from(...)` `.and(...)` `.par_apply(...)` instead of `.apply(...)`,
+/// so the traversal is split across rayon's thread pool. As with
+/// `Zip::par_apply`, there is no ordering guarantee across elements, so the
+/// body should be safe to run in any order. Requires the `rayon` feature.
+///
+/// ```
+/// extern crate ndarray;
+///
+/// use ndarray::Array2;
+/// use ndarray::par_azip;
+///
+/// fn main() {
+///     let arr_b = Array2::<f64>::from_elem((128, 128), 1.);
+///     let arr_d = Array2::<f64>::from_elem((128, 128), 2.);
+///     let mut c = Array2::<f64>::zeros((128, 128));
+///
+///     par_azip!((a in &mut c, &b in &arr_b, &d in &arr_d) { *a = b + d; });
+///
+///     assert_eq!(c, Array2::from_elem((128, 128), 3.));
+/// }
+/// ```
+#[macro_export]
+macro_rules! par_azip {
+    ( ($($t:tt)*) $body:block ) => {
+        $crate::__azip_impl!(@parse () () ($($t)*,) $body [par_apply])
+    };
+}
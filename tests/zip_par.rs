@@ -0,0 +1,53 @@
+#![cfg(feature = "rayon")]
+
+extern crate ndarray;
+
+use ndarray::parallel::prelude::*;
+use ndarray::parallel::MultiZip;
+use ndarray::{Array1, Array2, Axis, Zip};
+
+#[test]
+fn par_fold_matches_sequential_fold_above_split_threshold() {
+    // Large enough to force the producer to actually split (FOLD_MIN_LEN is
+    // 1024), so this exercises the split/reduce path rather than just a
+    // single unsplit chunk.
+    let n = 10_000;
+    let a = Array1::from((0..n).map(|i| i as f64).collect::<Vec<_>>());
+    let b = Array1::from((0..n).map(|i| i as f64 * 0.5).collect::<Vec<_>>());
+
+    let expected = a.iter().zip(&b).fold(0., |acc, (&a, &b)| acc + a * b);
+
+    let got = Zip::from(&a)
+        .and(&b)
+        .par_fold(|| 0., |acc, &a, &b| acc + a * b, |a, b| a + b);
+
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn multi_zip_truncates_to_shortest_member_both_directions() {
+    let a = Array2::<i32>::from_shape_fn((6, 1), |(i, _)| i as i32);
+    let b = Array2::<i32>::from_shape_fn((4, 1), |(i, _)| i as i32 * 10);
+
+    let sequential: Vec<(i32, i32)> = a
+        .axis_iter(Axis(0))
+        .zip(b.axis_iter(Axis(0)))
+        .map(|(a, b)| (a[0], b[0]))
+        .collect();
+
+    let forward: Vec<(i32, i32)> = MultiZip::new((a.axis_iter(Axis(0)), b.axis_iter(Axis(0))))
+        .into_par_iter()
+        .map(|(a, b)| (a[0], b[0]))
+        .collect();
+    assert_eq!(forward, sequential);
+
+    let mut expected_reversed = sequential;
+    expected_reversed.reverse();
+
+    let reversed: Vec<(i32, i32)> = MultiZip::new((a.axis_iter(Axis(0)), b.axis_iter(Axis(0))))
+        .into_par_iter()
+        .rev()
+        .map(|(a, b)| (a[0], b[0]))
+        .collect();
+    assert_eq!(reversed, expected_reversed);
+}